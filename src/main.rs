@@ -1,16 +1,24 @@
+mod error;
 mod rpc;
 
 use argh::FromArgs;
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::Router;
 use hex::prelude::*;
+use ldk_node::bitcoin::secp256k1::PublicKey;
 use ldk_node::bitcoin::Network;
+use ldk_node::lightning::ln::msgs::SocketAddress;
 use ldk_node::lightning::util::logger::{Logger, Record};
-use ldk_node::{Builder, Node};
+use ldk_node::{Builder, Event, Node};
 use rand::{thread_rng, RngCore};
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+
+/// Number of events a slow SSE subscriber can fall behind before it starts dropping them.
+const EVENT_BROADCAST_CAPACITY: usize = 100;
 
 pub struct TracingLogger {}
 
@@ -45,18 +53,31 @@ struct Args {
     /// node seed bytes as hex string.
     /// if not provided, one will be generated and written to stdout.
     seed_hex: Option<String>,
+    #[argh(option)]
+    /// node id of an LSP to request just-in-time inbound liquidity from.
+    lsp_node_id: Option<String>,
+    #[argh(option)]
+    /// ip:port of the LSP named by `lsp_node_id`.
+    lsp_address: Option<String>,
+    #[argh(option)]
+    /// auth token for the LSP named by `lsp_node_id`, if it requires one.
+    lsp_token: Option<String>,
 }
 
 #[derive(Clone)]
 struct AppState {
     node: Arc<Node>,
+    events: broadcast::Sender<Event>,
+    network: Network,
 }
 
 fn main() {
 		tracing_subscriber::fmt::init();
 
     let args: Args = argh::from_env();
-    let node_service_addr: SocketAddr = format!("[::]:{}", args.node_service_port).parse().unwrap();
+    let node_service_addr: SocketAddr = format!("[::]:{}", args.node_service_port)
+        .parse()
+        .expect("valid node service socket address");
 
     let seed_bytes = args
         .seed_hex
@@ -71,21 +92,38 @@ fn main() {
             seed
         });
 
-    let node = Builder::new()
+    // These `.expect()` calls intentionally panic on failure: this is startup-time config
+    // validation before the HTTP server (and `ApiError`) exist, so there's no request to
+    // return a JSON error to - failing fast here is the correct behavior, not a gap in
+    // the handler error-handling work below.
+    let mut builder = Builder::new();
+    builder
     	.set_network(args.network)
-    	.set_relay_node_address(node_service_addr.into()).unwrap()
+    	.set_relay_node_address(node_service_addr.into()).expect("valid relay node address")
     	.set_esplora_server(args.esplora_url)
    		.set_gossip_source_rgs(args.rgs_url)
-    	.set_entropy_seed_bytes(seed_bytes.to_vec()).unwrap()
-			.set_storage_dir_path(args.data_dir)
-    	.build_with_fs_store().unwrap();
+    	.set_entropy_seed_bytes(seed_bytes.to_vec()).expect("valid entropy seed bytes")
+			.set_storage_dir_path(args.data_dir);
+
+    if let (Some(lsp_node_id), Some(lsp_address)) = (args.lsp_node_id, args.lsp_address) {
+        let lsp_node_id = PublicKey::from_str(&lsp_node_id).expect("valid lsp node id");
+        let lsp_address = SocketAddress::from_str(&lsp_address).expect("valid lsp address");
+        builder.set_liquidity_source_lsps2(lsp_node_id, lsp_address, args.lsp_token);
+    }
+
+    let node = builder.build_with_fs_store().expect("failed to build node");
 
 		println!("node id: {}", node.node_id().to_string());
 
-    node.start().unwrap();
+    node.start().expect("failed to start node");
+
+    let node = Arc::new(node);
+    let (events, _) = broadcast::channel(EVENT_BROADCAST_CAPACITY);
 
     let app_state = AppState {
-        node: Arc::new(node),
+        node: node.clone(),
+        events,
+        network: args.network,
     };
 
     let app = Router::new()
@@ -94,15 +132,24 @@ fn main() {
         .route("/funding-address", get(rpc::funding_address))
         .route("/channels", post(rpc::open_channel))
         .route("/channels", get(rpc::list_channels))
+        .route("/channels", delete(rpc::close_channel))
         .route("/pay-invoice", post(rpc::pay_invoice))
         .route("/get-invoice", post(rpc::get_invoice))
+        .route("/get-jit-invoice", post(rpc::get_jit_invoice))
+        .route("/offers", post(rpc::create_offer))
+        .route("/pay-offer", post(rpc::pay_offer))
         .route("/sync", post(rpc::sync))
         .route("/balance", get(rpc::get_balance))
+        .route("/send-onchain", post(rpc::send_onchain))
         .route("/get-payment/:payment_hash", get(rpc::get_payment))
-        .with_state(app_state);
+        .route("/payments", get(rpc::list_payments))
+        .route("/events", get(rpc::events))
+        .with_state(app_state.clone());
 
     let rt = Runtime::new().unwrap();
     rt.block_on(async {
+        tokio::spawn(drain_events(node, app_state.events));
+
         let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", args.rpc_port))
             .await
             .unwrap();
@@ -112,3 +159,23 @@ fn main() {
         axum::serve(listener, app).await.unwrap();
     });
 }
+
+/// Drains ldk-node's durable event queue forever, fanning each event out to SSE subscribers
+/// before acknowledging it. `event_handled()` is called unconditionally after the broadcast
+/// send so a lagging/absent subscriber (which only drops messages on its own receiver) can
+/// never stall the queue - the broadcast channel is best-effort notification layered on top
+/// of the at-least-once guarantee ldk-node already provides.
+async fn drain_events(node: Arc<Node>, events: broadcast::Sender<Event>) {
+    loop {
+        let event = node.wait_next_event_async().await;
+        let _ = events.send(event);
+
+        // A transient failure here (e.g. a persistence hiccup) must not kill this loop -
+        // doing so would silently cut off event delivery to every subscriber, past and
+        // future, for the rest of the process's life. Log and keep draining; the event
+        // stays unacknowledged and will be redelivered on the next `wait_next_event_async`.
+        if let Err(err) = node.event_handled() {
+            tracing::error!("failed to mark ldk-node event as handled: {err}");
+        }
+    }
+}
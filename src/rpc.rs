@@ -1,19 +1,27 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
     Json,
 };
+use futures_util::{Stream, StreamExt};
 use hex::prelude::*;
 use hex::DisplayHex;
 use ldk_node::{
-    bitcoin::secp256k1::PublicKey,
-    lightning::ln::{msgs::SocketAddress, PaymentHash},
+    bitcoin::{secp256k1::PublicKey, Address, FeeRate},
+    lightning::{
+        ln::{msgs::SocketAddress, PaymentHash},
+        offers::offer::Offer,
+    },
     lightning_invoice::Bolt11Invoice,
-    ChannelDetails,
+    ChannelDetails, Event, PaymentDetails, PaymentDirection, UserChannelId,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::convert::Infallible;
 use std::str::FromStr;
+use tokio_stream::wrappers::BroadcastStream;
 
+use crate::error::ApiError;
 use crate::AppState;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,6 +124,8 @@ pub struct GetInvoiceResponse {
 pub struct GetBalanceResponse {
     pub total_onchain_balance_sats: u64,
     pub spendable_onchain_balance_sats: u64,
+    pub total_lightning_balance_msat: u64,
+    pub total_anchor_channels_reserve_sats: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,49 +134,166 @@ pub struct GetPaymentResponse {
     pub preimage: Option<String>,
 }
 
-pub(crate) async fn funding_address(State(state): State<AppState>) -> Json<FundingAddress> {
-    Json(FundingAddress {
-        address: state.node.new_onchain_address().unwrap().to_string(),
-    })
+/// A JSON-friendly, tagged projection of the ldk-node `Event`s we surface over SSE.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum NodeEvent {
+    PaymentReceived {
+        payment_hash: String,
+        amount_msat: u64,
+    },
+    PaymentSuccessful {
+        payment_hash: String,
+    },
+    PaymentFailed {
+        payment_hash: String,
+    },
+    ChannelReady {
+        channel_id: String,
+        user_channel_id: u128,
+    },
+    ChannelClosed {
+        channel_id: String,
+        user_channel_id: u128,
+        reason: Option<String>,
+    },
+    Other,
+}
+
+impl From<Event> for NodeEvent {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::PaymentReceived {
+                payment_hash,
+                amount_msat,
+                ..
+            } => NodeEvent::PaymentReceived {
+                payment_hash: payment_hash.0.to_lower_hex_string(),
+                amount_msat,
+            },
+            Event::PaymentSuccessful { payment_hash, .. } => NodeEvent::PaymentSuccessful {
+                payment_hash: payment_hash.0.to_lower_hex_string(),
+            },
+            Event::PaymentFailed { payment_hash, .. } => NodeEvent::PaymentFailed {
+                payment_hash: payment_hash
+                    .map(|hash| hash.0.to_lower_hex_string())
+                    .unwrap_or_default(),
+            },
+            Event::ChannelReady {
+                channel_id,
+                user_channel_id,
+                ..
+            } => NodeEvent::ChannelReady {
+                channel_id: channel_id.to_string(),
+                user_channel_id: user_channel_id.0,
+            },
+            Event::ChannelClosed {
+                channel_id,
+                user_channel_id,
+                reason,
+                ..
+            } => NodeEvent::ChannelClosed {
+                channel_id: channel_id.to_string(),
+                user_channel_id: user_channel_id.0,
+                reason: reason.map(|reason| reason.to_string()),
+            },
+            _ => NodeEvent::Other,
+        }
+    }
+}
+
+pub(crate) async fn events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(|event| async move {
+        // Lagged events are dropped here, not in `drain_events` - the durable queue was
+        // already acknowledged, so this is purely a best-effort notification gap.
+        let event = event.ok()?;
+        let sse_event = SseEvent::default()
+            .json_data(NodeEvent::from(event))
+            .expect("NodeEvent always serializes");
+        Some(Ok(sse_event))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+pub(crate) async fn funding_address(
+    State(state): State<AppState>,
+) -> Result<Json<FundingAddress>, ApiError> {
+    Ok(Json(FundingAddress {
+        address: state.node.new_onchain_address()?.to_string(),
+    }))
 }
 
 pub(crate) async fn open_channel(
     State(state): State<AppState>,
     Json(req): Json<OpenChannelRequest>,
-) -> Json<OpenChannelResponse> {
-    let socket_addr = SocketAddress::from_str(&req.ip_port).unwrap();
-    let res = state
-        .node
-        .connect_open_channel(
-            req.pubkey,
-            socket_addr,
-            req.funding_sats,
-            Some(req.push_sats * 1000),
-            None,
-            true,
-        )
-        .unwrap();
-
-    Json(OpenChannelResponse {
+) -> Result<Json<OpenChannelResponse>, ApiError> {
+    let socket_addr = SocketAddress::from_str(&req.ip_port)
+        .map_err(|err| ApiError::BadRequest(format!("invalid ip_port: {err}")))?;
+    let res = state.node.connect_open_channel(
+        req.pubkey,
+        socket_addr,
+        req.funding_sats,
+        Some(req.push_sats * 1000),
+        None,
+        true,
+    )?;
+
+    Ok(Json(OpenChannelResponse {
         user_channel_id: res.0,
-    })
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloseChannelRequest {
+    pub user_channel_id: u128,
+    pub counterparty_node_id: PublicKey,
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloseChannelResponse {
+    pub funding_txo: Option<String>,
+}
+
+pub(crate) async fn close_channel(
+    State(state): State<AppState>,
+    Json(req): Json<CloseChannelRequest>,
+) -> Result<Json<CloseChannelResponse>, ApiError> {
+    let user_channel_id = UserChannelId(req.user_channel_id);
+
+    let funding_txo = state
+        .node
+        .list_channels()
+        .into_iter()
+        .find(|channel| channel.user_channel_id == user_channel_id)
+        .and_then(|channel| channel.funding_txo)
+        .map(|outpoint| outpoint.to_string());
+
+    if req.force {
+        state
+            .node
+            .force_close_channel(&user_channel_id, req.counterparty_node_id)?;
+    } else {
+        state
+            .node
+            .close_channel(&user_channel_id, req.counterparty_node_id)?;
+    }
+
+    Ok(Json(CloseChannelResponse { funding_txo }))
 }
 
 pub(crate) async fn connect_peer(
   State(state): State<AppState>,
   Json(req): Json<ConnectPeerRequest>,
-) -> Json<ConnectPeerResponse> {
-  let socket_addr = SocketAddress::from_str(&req.ip_port).unwrap();
-  let _ = state
-      .node
-      .connect(
-          req.pubkey,
-          socket_addr,
-          false,
-      )
-      .unwrap();
+) -> Result<Json<ConnectPeerResponse>, ApiError> {
+  let socket_addr = SocketAddress::from_str(&req.ip_port)
+      .map_err(|err| ApiError::BadRequest(format!("invalid ip_port: {err}")))?;
+  state.node.connect(req.pubkey, socket_addr, false)?;
 
-  Json(ConnectPeerResponse {})
+  Ok(Json(ConnectPeerResponse {}))
 }
 
 pub(crate) async fn list_peers(
@@ -197,31 +324,117 @@ pub(crate) async fn list_channels(State(state): State<AppState>) -> Json<ListCha
 pub(crate) async fn pay_invoice(
     State(state): State<AppState>,
     Json(req): Json<PayInvoiceRequest>,
-) -> Json<PayInvoiceResponse> {
-    let invoice = Bolt11Invoice::from_str(&req.invoice).unwrap();
-    let res = state.node.send_payment(&invoice).unwrap();
-    Json(PayInvoiceResponse {
+) -> Result<Json<PayInvoiceResponse>, ApiError> {
+    let invoice = Bolt11Invoice::from_str(&req.invoice)
+        .map_err(|err| ApiError::BadRequest(format!("invalid invoice: {err}")))?;
+    let res = state.node.send_payment(&invoice)?;
+    Ok(Json(PayInvoiceResponse {
         payment_hash: res.0.to_lower_hex_string(),
-    })
+    }))
 }
 
 pub(crate) async fn get_invoice(
     State(state): State<AppState>,
     Json(req): Json<GetInvoiceRequest>,
-) -> Json<GetInvoiceResponse> {
-    let invoice = state
-        .node
-        .receive_payment(req.amount_sats * 1000, &req.description, req.expiry_secs)
-        .unwrap();
+) -> Result<Json<GetInvoiceResponse>, ApiError> {
+    let invoice =
+        state
+            .node
+            .receive_payment(req.amount_sats * 1000, &req.description, req.expiry_secs)?;
 
-    Json(GetInvoiceResponse {
+    Ok(Json(GetInvoiceResponse {
         invoice: invoice.to_string(),
-    })
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetJitInvoiceRequest {
+    pub amount_sats: u64,
+    pub description: String,
+    pub expiry_secs: u32,
+    pub max_proportional_lsp_fee_limit_ppm: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetJitInvoiceResponse {
+    pub invoice: String,
+    // ldk-node has no synchronous quote for the LSP's opening fee at invoice-creation
+    // time - the LSP only settles on a fee once the JIT channel actually opens. This is
+    // just the cap the caller supplied on the request, echoed back; it is NOT a quote.
+    pub requested_max_fee_limit_ppm: Option<u64>,
 }
 
-pub(crate) async fn sync(State(state): State<AppState>) -> Json<Value> {
-    state.node.sync_wallets().unwrap();
-    Json(json!({"synced": true}))
+pub(crate) async fn get_jit_invoice(
+    State(state): State<AppState>,
+    Json(req): Json<GetJitInvoiceRequest>,
+) -> Result<Json<GetJitInvoiceResponse>, ApiError> {
+    let invoice = state.node.receive_payment_via_jit_channel(
+        req.amount_sats * 1000,
+        &req.description,
+        req.expiry_secs,
+        req.max_proportional_lsp_fee_limit_ppm,
+    )?;
+
+    Ok(Json(GetJitInvoiceResponse {
+        invoice: invoice.to_string(),
+        requested_max_fee_limit_ppm: req.max_proportional_lsp_fee_limit_ppm,
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateOfferRequest {
+    pub amount_sats: Option<u64>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateOfferResponse {
+    pub offer: String,
+}
+
+pub(crate) async fn create_offer(
+    State(state): State<AppState>,
+    Json(req): Json<CreateOfferRequest>,
+) -> Result<Json<CreateOfferResponse>, ApiError> {
+    let offer = state.node.receive_payment_via_offer(
+        req.amount_sats.map(|amount_sats| amount_sats * 1000),
+        req.description.as_deref().unwrap_or(""),
+    )?;
+
+    Ok(Json(CreateOfferResponse {
+        offer: offer.to_string(),
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayOfferRequest {
+    pub offer: String,
+    pub amount_sats: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayOfferResponse {
+    pub payment_id: String,
+}
+
+pub(crate) async fn pay_offer(
+    State(state): State<AppState>,
+    Json(req): Json<PayOfferRequest>,
+) -> Result<Json<PayOfferResponse>, ApiError> {
+    let offer = Offer::from_str(&req.offer)
+        .map_err(|_| ApiError::BadRequest("invalid offer".to_string()))?;
+    let payment_id = state
+        .node
+        .pay_offer(&offer, req.amount_sats.map(|amount_sats| amount_sats * 1000))?;
+
+    Ok(Json(PayOfferResponse {
+        payment_id: payment_id.0.to_lower_hex_string(),
+    }))
+}
+
+pub(crate) async fn sync(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    state.node.sync_wallets()?;
+    Ok(Json(json!({"synced": true})))
 }
 
 pub(crate) async fn get_balance(State(state): State<AppState>) -> Json<GetBalanceResponse> {
@@ -229,18 +442,205 @@ pub(crate) async fn get_balance(State(state): State<AppState>) -> Json<GetBalanc
     Json(GetBalanceResponse {
         total_onchain_balance_sats: balances.total_onchain_balance_sats,
         spendable_onchain_balance_sats: balances.spendable_onchain_balance_sats,
+        total_lightning_balance_msat: balances.total_lightning_balance_msat,
+        total_anchor_channels_reserve_sats: balances.total_anchor_channels_reserve_sats,
     })
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendOnchainRequest {
+    pub address: String,
+    pub amount_sats: Option<u64>,
+    pub sat_per_vb: Option<u32>,
+    #[serde(default)]
+    pub drain: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendOnchainResponse {
+    pub txid: String,
+}
+
+pub(crate) async fn send_onchain(
+    State(state): State<AppState>,
+    Json(req): Json<SendOnchainRequest>,
+) -> Result<Json<SendOnchainResponse>, ApiError> {
+    let address = Address::from_str(&req.address)
+        .map_err(|err| ApiError::BadRequest(format!("invalid address: {err}")))?
+        .require_network(state.network)
+        .map_err(|err| ApiError::BadRequest(format!("address network mismatch: {err}")))?;
+    let fee_rate = req
+        .sat_per_vb
+        .map(|sat_per_vb| {
+            FeeRate::from_sat_per_vb(sat_per_vb as u64)
+                .ok_or_else(|| ApiError::BadRequest("invalid sat_per_vb".to_string()))
+        })
+        .transpose()?;
+
+    let txid = if req.drain {
+        state.node.send_all_to_onchain_address(&address, fee_rate)?
+    } else {
+        let amount_sats = req.amount_sats.ok_or_else(|| {
+            ApiError::BadRequest("amount_sats required unless drain is set".to_string())
+        })?;
+        state
+            .node
+            .send_to_onchain_address(&address, amount_sats, fee_rate)?
+    };
+
+    Ok(Json(SendOnchainResponse {
+        txid: txid.to_string(),
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentSummary {
+    pub payment_hash: String,
+    pub direction: String,
+    pub amount_msat: Option<u64>,
+    pub status: String,
+    pub preimage: Option<String>,
+    // This version of ldk-node doesn't track a payment timestamp; left here so clients
+    // don't need to change shape once it does.
+    pub timestamp: Option<u64>,
+}
+
+impl From<PaymentDetails> for PaymentSummary {
+    fn from(payment: PaymentDetails) -> Self {
+        Self {
+            payment_hash: payment.hash.0.to_lower_hex_string(),
+            direction: match payment.direction {
+                PaymentDirection::Inbound => "inbound".to_string(),
+                PaymentDirection::Outbound => "outbound".to_string(),
+            },
+            amount_msat: payment.amount_msat,
+            status: match payment.status {
+                ldk_node::PaymentStatus::Pending => "pending".to_string(),
+                ldk_node::PaymentStatus::Succeeded => "succeeded".to_string(),
+                ldk_node::PaymentStatus::Failed => "failed".to_string(),
+            },
+            preimage: payment
+                .preimage
+                .map(|preimage| preimage.0.to_lower_hex_string()),
+            timestamp: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaymentStatusFilter {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl PaymentStatusFilter {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for PaymentStatusFilter {
+    type Err = ApiError;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "pending" => Ok(Self::Pending),
+            "succeeded" => Ok(Self::Succeeded),
+            "failed" => Ok(Self::Failed),
+            other => Err(ApiError::BadRequest(format!(
+                "unknown status filter '{other}', expected pending|succeeded|failed"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaymentDirectionFilter {
+    Inbound,
+    Outbound,
+}
+
+impl PaymentDirectionFilter {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Inbound => "inbound",
+            Self::Outbound => "outbound",
+        }
+    }
+}
+
+impl FromStr for PaymentDirectionFilter {
+    type Err = ApiError;
+
+    fn from_str(direction: &str) -> Result<Self, Self::Err> {
+        match direction {
+            "inbound" => Ok(Self::Inbound),
+            "outbound" => Ok(Self::Outbound),
+            other => Err(ApiError::BadRequest(format!(
+                "unknown direction filter '{other}', expected inbound|outbound"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListPaymentsQuery {
+    pub status: Option<String>,
+    pub direction: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListPaymentsResponse {
+    pub payments: Vec<PaymentSummary>,
+}
+
+pub(crate) async fn list_payments(
+    State(state): State<AppState>,
+    Query(query): Query<ListPaymentsQuery>,
+) -> Result<Json<ListPaymentsResponse>, ApiError> {
+    let status = query
+        .status
+        .as_deref()
+        .map(PaymentStatusFilter::from_str)
+        .transpose()?;
+    let direction = query
+        .direction
+        .as_deref()
+        .map(PaymentDirectionFilter::from_str)
+        .transpose()?;
+
+    let payments = state
+        .node
+        .list_payments()
+        .into_iter()
+        .map(PaymentSummary::from)
+        .filter(|payment| status.map_or(true, |status| payment.status == status.as_str()))
+        .filter(|payment| direction.map_or(true, |direction| payment.direction == direction.as_str()))
+        .collect();
+
+    Ok(Json(ListPaymentsResponse { payments }))
+}
+
 pub(crate) async fn get_payment(
     State(state): State<AppState>,
     Path(payment_hash): Path<String>,
-) -> Json<GetPaymentResponse> {
-    let payment_hash_bytes = <[u8; 32]>::from_hex(&payment_hash).unwrap();
+) -> Result<Json<GetPaymentResponse>, ApiError> {
+    let payment_hash_bytes = <[u8; 32]>::from_hex(&payment_hash)
+        .map_err(|err| ApiError::BadRequest(format!("invalid payment_hash: {err}")))?;
     let payment_hash = PaymentHash(payment_hash_bytes);
-    let payment = state.node.payment(&payment_hash).unwrap();
-
-    Json(GetPaymentResponse {
+    let payment = state.node.payment(&payment_hash).ok_or_else(|| {
+        ApiError::NotFound(format!(
+            "no payment for hash {}",
+            payment_hash.0.to_lower_hex_string()
+        ))
+    })?;
+
+    Ok(Json(GetPaymentResponse {
         status: match payment.status {
             ldk_node::PaymentStatus::Pending => "pending".to_string(),
             ldk_node::PaymentStatus::Succeeded => "succeeded".to_string(),
@@ -249,5 +649,5 @@ pub(crate) async fn get_payment(
         preimage: payment
             .preimage
             .map(|preimage| preimage.0.to_lower_hex_string()),
-    })
+    }))
 }